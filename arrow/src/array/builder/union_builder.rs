@@ -17,13 +17,19 @@
 
 use std::any::Any;
 use std::collections::HashMap;
+use std::fmt;
 
+use crate::array::transform::MutableArrayData;
+use crate::array::Array;
+use crate::array::ArrayData;
 use crate::array::ArrayDataBuilder;
+use crate::array::ArrayRef;
 use crate::array::Int32BufferBuilder;
 use crate::array::Int8BufferBuilder;
 use crate::array::UnionArray;
 use crate::buffer::Buffer;
 
+use crate::array::{make_builder, ArrayBuilder};
 use crate::datatypes::DataType;
 use crate::datatypes::Field;
 use crate::datatypes::{ArrowNativeType, ArrowPrimitiveType};
@@ -33,22 +39,8 @@ use super::{BufferBuilder, NullBufferBuilder};
 
 use crate::array::make_array;
 
-/// `FieldData` is a helper struct to track the state of the fields in the `UnionBuilder`.
-#[derive(Debug)]
-struct FieldData {
-    /// The type id for this field
-    type_id: i8,
-    /// The Arrow data type represented in the `values_buffer`, which is untyped
-    data_type: DataType,
-    /// A buffer containing the values for this field in raw bytes
-    values_buffer: Box<dyn FieldDataValues>,
-    ///  The number of array slots represented by the buffer
-    slots: usize,
-    /// A builder for the null bitmap
-    null_buffer_builder: NullBufferBuilder,
-}
-
-/// A type-erased [`BufferBuilder`] used by [`FieldData`]
+/// A type-erased [`BufferBuilder`] used by [`FieldData`] to hold the values
+/// of a fixed-width primitive child.
 trait FieldDataValues: std::fmt::Debug {
     fn as_mut_any(&mut self) -> &mut dyn Any;
 
@@ -71,38 +63,213 @@ impl<T: ArrowNativeType> FieldDataValues for BufferBuilder<T> {
     }
 }
 
+/// The storage backing a single child of the [`UnionBuilder`].
+///
+/// Fixed-width primitives are stored directly as a raw value buffer plus a
+/// null buffer, without going through a type-erased [`ArrayBuilder`]. Any
+/// other Arrow type (`Utf8`, `Binary`, `List`, `Struct`, ...) is instead
+/// tracked by its own [`ArrayBuilder`], which already knows how to manage
+/// whatever offset/value/null buffers that type requires.
+enum FieldStorage {
+    Primitive {
+        values_buffer: Box<dyn FieldDataValues>,
+        null_buffer_builder: NullBufferBuilder,
+    },
+    Array(Box<dyn ArrayBuilder>),
+}
+
+impl fmt::Debug for FieldStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldStorage::Primitive { .. } => f.debug_struct("FieldStorage::Primitive").finish(),
+            FieldStorage::Array(_) => f.debug_struct("FieldStorage::Array").finish(),
+        }
+    }
+}
+
+/// `FieldData` is a helper struct to track the state of the fields in the `UnionBuilder`.
+#[derive(Debug)]
+struct FieldData {
+    /// The type id for this field
+    type_id: i8,
+    /// The Arrow data type represented by this field
+    data_type: DataType,
+    ///  The number of array slots represented by this field
+    slots: usize,
+    /// The builder backing this field's values
+    storage: FieldStorage,
+    /// For sparse unions only: the global row index at which each of this
+    /// field's `slots` values was appended, in increasing order. `None` for
+    /// dense unions, whose per-child offsets already make gaps unnecessary.
+    ///
+    /// Tracking positions instead of eagerly padding every other field on
+    /// every append keeps `UnionBuilder::append` O(1) regardless of the
+    /// number of fields; the gaps implied by missing positions are
+    /// materialized with nulls lazily, in `UnionBuilder::build`.
+    sparse_positions: Option<Vec<usize>>,
+}
+
 impl FieldData {
-    /// Creates a new `FieldData`.
-    fn new<T: ArrowPrimitiveType>(type_id: i8, data_type: DataType) -> Self {
+    /// Creates a new `FieldData` for a fixed-width primitive child.
+    fn new<T: ArrowPrimitiveType>(type_id: i8, data_type: DataType, sparse: bool) -> Self {
         Self {
             type_id,
             data_type,
             slots: 0,
-            values_buffer: Box::new(BufferBuilder::<T::Native>::new(1)),
-            null_buffer_builder: NullBufferBuilder::new(1),
+            storage: FieldStorage::Primitive {
+                values_buffer: Box::new(BufferBuilder::<T::Native>::new(1)),
+                null_buffer_builder: NullBufferBuilder::new(1),
+            },
+            sparse_positions: sparse.then(Vec::new),
         }
     }
 
-    /// Appends a single value to this `FieldData`'s `values_buffer`.
+    /// Creates a new `FieldData` for a non-primitive child, backed by the
+    /// [`ArrayBuilder`] appropriate for `data_type`.
+    fn new_array(type_id: i8, data_type: DataType, sparse: bool) -> Self {
+        let builder = make_builder(&data_type, 1);
+        Self {
+            type_id,
+            data_type,
+            slots: 0,
+            storage: FieldStorage::Array(builder),
+            sparse_positions: sparse.then(Vec::new),
+        }
+    }
+
+    /// Appends a single primitive value to this `FieldData`.
     fn append_value<T: ArrowPrimitiveType>(&mut self, v: T::Native) {
-        self.values_buffer
-            .as_mut_any()
-            .downcast_mut::<BufferBuilder<T::Native>>()
-            .expect("Tried to append unexpected type")
-            .append(v);
+        match &mut self.storage {
+            FieldStorage::Primitive {
+                values_buffer,
+                null_buffer_builder,
+            } => {
+                values_buffer
+                    .as_mut_any()
+                    .downcast_mut::<BufferBuilder<T::Native>>()
+                    .expect("Tried to append unexpected type")
+                    .append(v);
+                null_buffer_builder.append(true);
+            }
+            FieldStorage::Array(_) => {
+                panic!("Tried to append a primitive value to a non-primitive union field")
+            }
+        }
+        self.slots += 1;
+    }
 
-        self.null_buffer_builder.append(true);
+    /// Returns `true` if this field is backed by a type-erased
+    /// [`ArrayBuilder`] (a non-primitive child created via
+    /// [`UnionBuilder::append_any`]) rather than a primitive
+    /// `BufferBuilder`.
+    fn is_array_backed(&self) -> bool {
+        matches!(self.storage, FieldStorage::Array(_))
+    }
+
+    /// Appends a value to a non-primitive `FieldData` by driving its
+    /// [`ArrayBuilder`] directly.
+    fn append_array_value(&mut self, append: impl FnOnce(&mut dyn ArrayBuilder)) {
+        match &mut self.storage {
+            FieldStorage::Array(builder) => append(builder.as_mut()),
+            FieldStorage::Primitive { .. } => {
+                panic!("Tried to append a non-primitive value to a primitive union field")
+            }
+        }
         self.slots += 1;
     }
 
-    /// Appends a null to this `FieldData`.
+    /// Appends an explicit null row to this primitive `FieldData`, in both
+    /// dense and sparse unions. Panics if the field is non-primitive.
+    ///
+    /// The null padding a sparse union needs in this field for rows that
+    /// belong to other fields is never appended here: a missing
+    /// [`Self::sparse_positions`] entry is enough for [`fill_sparse_gaps`] to
+    /// materialize it lazily at [`UnionBuilder::build`] time.
     fn append_null(&mut self) {
-        self.values_buffer.append_null();
-        self.null_buffer_builder.append(false);
+        match &mut self.storage {
+            FieldStorage::Primitive {
+                values_buffer,
+                null_buffer_builder,
+            } => {
+                values_buffer.append_null();
+                null_buffer_builder.append(false);
+            }
+            FieldStorage::Array(_) => {
+                panic!("Tried to append a primitive null to a non-primitive union field")
+            }
+        }
         self.slots += 1;
     }
 }
 
+/// Expands a sparse union child from its compact representation (one slot
+/// per row the field actually received a value for) out to `len` slots,
+/// filling the gaps described by `positions` with nulls.
+///
+/// `positions` holds the row index of each of `compact`'s slots, in
+/// increasing order. If the field received a value on every row there are no
+/// gaps to fill and `compact` is returned unchanged.
+fn fill_sparse_gaps(compact: ArrayData, positions: &[usize], len: usize) -> ArrayRef {
+    if positions.len() == len {
+        return make_array(compact);
+    }
+
+    let mut mutable = MutableArrayData::new(vec![&compact], true, len);
+    let mut next = 0;
+    for row in 0..len {
+        if next < positions.len() && positions[next] == row {
+            mutable.extend(0, next, next + 1);
+            next += 1;
+        } else {
+            mutable.extend_nulls(1);
+        }
+    }
+    make_array(mutable.freeze())
+}
+
+/// Compares two `UnionArray`s for structural (element-wise) equality.
+///
+/// A slot only compares equal if both arrays select the same type id at
+/// that slot — two arrays that happen to hold an equal value in
+/// different variants are not equal. Otherwise each slot is resolved
+/// through its (dense) offset or (sparse) position — both exposed
+/// identically via [`UnionArray::value_offset`] — down to the referenced
+/// child value, so dense and sparse encodings of the same logical
+/// sequence compare equal. A null in one array's child at a slot is only
+/// equal to a null in the other's; the underlying (don't-care) bytes behind
+/// a null are never compared.
+///
+/// This belongs as `UnionArray`'s `PartialEq` impl alongside the rest of its
+/// definition; it lives here, next to [`UnionBuilder`], only because this
+/// snapshot of the crate doesn't include `array/union_array.rs`.
+pub fn union_arrays_eq(a: &UnionArray, b: &UnionArray) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    (0..a.len()).all(|i| {
+        if a.type_id(i) != b.type_id(i) {
+            return false;
+        }
+
+        let a_child = a.child(a.type_id(i));
+        let b_child = b.child(b.type_id(i));
+        if a_child.data_type() != b_child.data_type() {
+            return false;
+        }
+
+        let a_idx = a.value_offset(i) as usize;
+        let b_idx = b.value_offset(i) as usize;
+        match (a_child.is_null(a_idx), b_child.is_null(b_idx)) {
+            (true, true) => true,
+            (false, false) => {
+                a_child.slice(a_idx, 1).to_data() == b_child.slice(b_idx, 1).to_data()
+            }
+            _ => false,
+        }
+    })
+}
+
 /// Builder type for creating a new `UnionArray`.
 ///
 /// Example: **Dense Memory Layout**
@@ -202,101 +369,559 @@ impl UnionBuilder {
 
     /// Appends a value to this builder.
     #[inline]
-    pub fn append<T: ArrowPrimitiveType>(
+    pub fn append<T: ArrowPrimitiveType>(&mut self, type_name: &str, v: T::Native) -> Result<()> {
+        self.append_option::<T>(type_name, Some(v))
+    }
+
+    /// Appends a value to this builder, assigning `type_name` the explicit
+    /// `type_id` rather than letting the builder pick one.
+    ///
+    /// This is useful when the resulting `UnionArray` must match an external
+    /// schema where the type id space is caller-defined (and potentially
+    /// sparse), rather than a dense `0..n` range assigned in insertion order.
+    /// `type_id` must be consistent across all appends to `type_name`, and
+    /// must not already be in use by a different field.
+    #[inline]
+    pub fn append_with_type_id<T: ArrowPrimitiveType>(
         &mut self,
         type_name: &str,
+        type_id: i8,
         v: T::Native,
     ) -> Result<()> {
-        self.append_option::<T>(type_name, Some(v))
+        self.append_option_with_type_id::<T>(type_name, type_id, Some(v))
+    }
+
+    /// Returns the next type id to assign automatically: the smallest
+    /// non-negative id not already claimed by an existing field, so ids stay
+    /// low and dense even if a caller has reserved a higher (or negative) one
+    /// explicitly via [`Self::append_with_type_id`].
+    ///
+    /// Returns an error if every non-negative `i8` type id (0..=127) is
+    /// already claimed.
+    fn next_type_id(&self) -> Result<i8> {
+        (0..=i8::MAX)
+            .find(|candidate| !self.fields.values().any(|fd| fd.type_id == *candidate))
+            .ok_or_else(|| {
+                ArrowError::InvalidArgumentError(
+                    "Cannot assign a new type_id: ids 0..=127 are all already in use".to_string(),
+                )
+            })
+    }
+
+    /// Checks that `type_id` isn't already claimed by a field other than
+    /// `type_name`.
+    fn check_type_id_available(&self, type_id: i8, type_name: &str) -> Result<()> {
+        if let Some(other) = self
+            .fields
+            .iter()
+            .find(|(name, fd)| fd.type_id == type_id && name.as_str() != type_name)
+            .map(|(name, _)| name.clone())
+        {
+            return Err(ArrowError::InvalidArgumentError(format!(
+                "Cannot assign type_id {type_id} to field \"{type_name}\": already in use by field \"{other}\""
+            )));
+        }
+        Ok(())
+    }
+
+    /// Records the type id and offset/position bookkeeping shared by every
+    /// row, regardless of whether the value being appended is primitive or
+    /// not. `field_data` must already have been removed from `self.fields`.
+    ///
+    /// This is O(1): for a sparse union, the only bookkeeping is recording
+    /// the current row against `field_data`'s own position list. The other
+    /// fields are left untouched; their gaps are filled with nulls lazily in
+    /// `build`.
+    fn prepare_row(&mut self, field_data: &mut FieldData) {
+        self.type_id_builder.append(field_data.type_id);
+
+        match &mut self.value_offset_builder {
+            // Dense Union
+            Some(offset_builder) => {
+                offset_builder.append(field_data.slots as i32);
+            }
+            // Sparse Union
+            None => {
+                if let Some(positions) = &mut field_data.sparse_positions {
+                    positions.push(self.len);
+                }
+            }
+        }
     }
 
     fn append_option<T: ArrowPrimitiveType>(
         &mut self,
         type_name: &str,
         v: Option<T::Native>,
+    ) -> Result<()> {
+        let type_id = match self.fields.get(type_name) {
+            Some(fd) => fd.type_id,
+            None => self.next_type_id()?,
+        };
+        self.append_option_with_type_id::<T>(type_name, type_id, v)
+    }
+
+    fn append_option_with_type_id<T: ArrowPrimitiveType>(
+        &mut self,
+        type_name: &str,
+        type_id: i8,
+        v: Option<T::Native>,
     ) -> Result<()> {
         let type_name = type_name.to_string();
 
         let mut field_data = match self.fields.remove(&type_name) {
             Some(data) => {
                 if data.data_type != T::DATA_TYPE {
-                    return Err(ArrowError::InvalidArgumentError(format!("Attempt to write col \"{}\" with type {} doesn't match existing type {}", type_name, T::DATA_TYPE, data.data_type)));
+                    return Err(ArrowError::InvalidArgumentError(format!(
+                        "Attempt to write col \"{}\" with type {} doesn't match existing type {}",
+                        type_name,
+                        T::DATA_TYPE,
+                        data.data_type
+                    )));
+                }
+                if data.type_id != type_id {
+                    return Err(ArrowError::InvalidArgumentError(format!("Attempt to write col \"{}\" with type_id {} doesn't match existing type_id {}", type_name, type_id, data.type_id)));
+                }
+                if data.is_array_backed() {
+                    return Err(ArrowError::InvalidArgumentError(format!(
+                        "Attempt to write col \"{type_name}\" as a primitive value, but it was already created via append_any"
+                    )));
+                }
+                data
+            }
+            None => {
+                self.check_type_id_available(type_id, &type_name)?;
+                FieldData::new::<T>(type_id, T::DATA_TYPE, self.value_offset_builder.is_none())
+            }
+        };
+
+        self.prepare_row(&mut field_data);
+
+        match v {
+            Some(v) => field_data.append_value::<T>(v),
+            None => field_data.append_null(),
+        }
+
+        self.fields.insert(type_name, field_data);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Appends a value to a non-primitive child of this builder (`Utf8`,
+    /// `Binary`, `List`, `Struct`, ...) by driving that child's own
+    /// [`ArrayBuilder`].
+    ///
+    /// Unlike [`Self::append`], which only knows how to write into a
+    /// `BufferBuilder` of fixed-width primitives, `append` here is a closure
+    /// that drives whichever builder [`make_builder`] constructs for
+    /// `data_type`, e.g.:
+    ///
+    /// ```
+    /// use arrow::array::{ArrayBuilder, StringBuilder, UnionBuilder};
+    /// use arrow::datatypes::DataType;
+    ///
+    /// let mut builder = UnionBuilder::new_dense();
+    /// builder
+    ///     .append_any("a", DataType::Utf8, |b| {
+    ///         b.as_any_mut()
+    ///             .downcast_mut::<StringBuilder>()
+    ///             .unwrap()
+    ///             .append_value("foo")
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn append_any(
+        &mut self,
+        type_name: &str,
+        data_type: DataType,
+        append: impl FnOnce(&mut dyn ArrayBuilder),
+    ) -> Result<()> {
+        if data_type.is_primitive() {
+            return Err(ArrowError::InvalidArgumentError(format!(
+                "append_any does not support primitive type {data_type}; use UnionBuilder::append or append_with_type_id instead"
+            )));
+        }
+
+        let type_name = type_name.to_string();
+
+        let mut field_data = match self.fields.remove(&type_name) {
+            Some(data) => {
+                if data.data_type != data_type {
+                    return Err(ArrowError::InvalidArgumentError(format!(
+                        "Attempt to write col \"{}\" with type {} doesn't match existing type {}",
+                        type_name, data_type, data.data_type
+                    )));
+                }
+                if !data.is_array_backed() {
+                    return Err(ArrowError::InvalidArgumentError(format!(
+                        "Attempt to write col \"{type_name}\" via append_any, but it was already created as a primitive field"
+                    )));
                 }
                 data
             }
-            None => match self.value_offset_builder {
-                Some(_) => FieldData::new::<T>(self.fields.len() as i8, T::DATA_TYPE),
-                None => {
-                    let mut fd =
-                        FieldData::new::<T>(self.fields.len() as i8, T::DATA_TYPE);
-                    for _ in 0..self.len {
-                        fd.append_null();
-                    }
-                    fd
+            None => FieldData::new_array(
+                self.next_type_id()?,
+                data_type,
+                self.value_offset_builder.is_none(),
+            ),
+        };
+
+        self.prepare_row(&mut field_data);
+        field_data.append_array_value(append);
+
+        self.fields.insert(type_name, field_data);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Appends a slice of non-null primitive values to `type_name` in one
+    /// call.
+    ///
+    /// This is a bulk counterpart to [`Self::append`]: the field is looked
+    /// up once rather than once per value, and the underlying buffers are
+    /// grown by `values.len()` in a single reservation instead of one
+    /// allocation per element.
+    #[inline]
+    pub fn append_slice<T: ArrowPrimitiveType>(
+        &mut self,
+        type_name: &str,
+        values: &[T::Native],
+    ) -> Result<()> {
+        self.append_values::<T>(type_name, values, None)
+    }
+
+    /// Appends `values` to `type_name` in one call, with `validity` marking
+    /// which of them are null (`None` means all of `values` are valid).
+    ///
+    /// Like [`Self::append_slice`], this looks the field up once and grows
+    /// its buffers in bulk rather than doing a `HashMap` round trip and a
+    /// buffer push per value.
+    pub fn append_values<T: ArrowPrimitiveType>(
+        &mut self,
+        type_name: &str,
+        values: &[T::Native],
+        validity: Option<&[bool]>,
+    ) -> Result<()> {
+        if let Some(validity) = validity {
+            if validity.len() != values.len() {
+                return Err(ArrowError::InvalidArgumentError(format!(
+                    "validity length {} doesn't match values length {}",
+                    validity.len(),
+                    values.len()
+                )));
+            }
+        }
+
+        let n = values.len();
+        if n == 0 {
+            return Ok(());
+        }
+
+        let mut field_data = match self.fields.remove(type_name) {
+            Some(data) => {
+                if data.is_array_backed() {
+                    return Err(ArrowError::InvalidArgumentError(format!(
+                        "Attempt to bulk-append primitive values to col \"{type_name}\", but it was already created via append_any"
+                    )));
                 }
-            },
+                if data.data_type != T::DATA_TYPE {
+                    return Err(ArrowError::InvalidArgumentError(format!(
+                        "Attempt to write col \"{}\" with type {} doesn't match existing type {}",
+                        type_name,
+                        T::DATA_TYPE,
+                        data.data_type
+                    )));
+                }
+                data
+            }
+            None => {
+                let type_id = self.next_type_id()?;
+                self.check_type_id_available(type_id, type_name)?;
+                FieldData::new::<T>(type_id, T::DATA_TYPE, self.value_offset_builder.is_none())
+            }
         };
-        self.type_id_builder.append(field_data.type_id);
+
+        self.type_id_builder.reserve(n);
+        self.type_id_builder.append_n(n, field_data.type_id);
 
         match &mut self.value_offset_builder {
-            // Dense Union
+            // Dense Union: each row's offset is the next free slot in this
+            // field's compact buffer.
             Some(offset_builder) => {
-                offset_builder.append(field_data.slots as i32);
+                offset_builder.reserve(n);
+                for i in 0..n {
+                    offset_builder.append((field_data.slots + i) as i32);
+                }
             }
-            // Sparse Union
+            // Sparse Union: thanks to the lazy gap-filling in `build`, a
+            // bulk append just records one position per new row, with no
+            // need to pad any other field.
             None => {
-                for (_, fd) in self.fields.iter_mut() {
-                    // Append to all bar the FieldData currently being appended to
-                    fd.append_null();
+                if let Some(positions) = &mut field_data.sparse_positions {
+                    positions.reserve(n);
+                    positions.extend(self.len..self.len + n);
                 }
             }
         }
 
-        match v {
-            Some(v) => field_data.append_value::<T>(v),
-            None => field_data.append_null(),
+        match &mut field_data.storage {
+            FieldStorage::Primitive {
+                values_buffer,
+                null_buffer_builder,
+            } => {
+                let values_buffer = values_buffer
+                    .as_mut_any()
+                    .downcast_mut::<BufferBuilder<T::Native>>()
+                    .expect("Tried to append unexpected type");
+                values_buffer.reserve(n);
+                values_buffer.append_slice(values);
+
+                null_buffer_builder.reserve(n);
+                match validity {
+                    Some(validity) => null_buffer_builder.append_slice(validity),
+                    None => null_buffer_builder.append_n(n, true),
+                }
+            }
+            FieldStorage::Array(_) => {
+                unreachable!("storage kind was already checked above")
+            }
         }
+        field_data.slots += n;
 
-        self.fields.insert(type_name, field_data);
-        self.len += 1;
+        self.fields.insert(type_name.to_string(), field_data);
+        self.len += n;
         Ok(())
     }
 
     /// Builds this builder creating a new `UnionArray`.
+    ///
+    /// Each child keeps the type id it was assigned at first insertion,
+    /// whether that id was chosen automatically or pinned via
+    /// [`Self::append_with_type_id`] — `build` does not renumber children
+    /// into a dense `0..n` range.
     pub fn build(mut self) -> Result<UnionArray> {
         let type_id_buffer = self.type_id_builder.finish();
         let value_offsets_buffer = self.value_offset_builder.map(|mut b| b.finish());
+        let len = self.len;
         let mut children = Vec::new();
         for (
             name,
             FieldData {
                 type_id,
                 data_type,
-                mut values_buffer,
                 slots,
-                null_buffer_builder: mut bitmap_builder,
+                storage,
+                sparse_positions,
             },
         ) in self.fields.into_iter()
         {
-            let buffer = values_buffer.finish();
-            let arr_data_builder = ArrayDataBuilder::new(data_type.clone())
-                .add_buffer(buffer)
-                .len(slots)
-                .null_bit_buffer(bitmap_builder.finish());
-
-            let arr_data_ref = unsafe { arr_data_builder.build_unchecked() };
-            let array_ref = make_array(arr_data_ref);
+            let compact = match storage {
+                FieldStorage::Primitive {
+                    mut values_buffer,
+                    null_buffer_builder: mut bitmap_builder,
+                } => {
+                    let buffer = values_buffer.finish();
+                    let arr_data_builder = ArrayDataBuilder::new(data_type.clone())
+                        .add_buffer(buffer)
+                        .len(slots)
+                        .null_bit_buffer(bitmap_builder.finish());
+
+                    unsafe { arr_data_builder.build_unchecked() }
+                }
+                FieldStorage::Array(mut builder) => builder.finish().to_data(),
+            };
+
+            let array_ref = match sparse_positions {
+                Some(positions) => fill_sparse_gaps(compact, &positions, len),
+                None => make_array(compact),
+            };
             children.push((type_id, (Field::new(&name, data_type, false), array_ref)))
         }
 
-        children.sort_by(|a, b| {
-            a.0.partial_cmp(&b.0)
-                .expect("This will never be None as type ids are always i8 values.")
-        });
-        let children: Vec<_> = children.into_iter().map(|(_, b)| b).collect();
+        children.sort_by_key(|(type_id, _)| *type_id);
 
-        let type_ids: Vec<i8> = (0_i8..children.len() as i8).collect();
+        let type_ids: Vec<i8> = children.iter().map(|(type_id, _)| *type_id).collect();
+        let children: Vec<_> = children.into_iter().map(|(_, b)| b).collect();
 
         UnionArray::try_new(&type_ids, type_id_buffer, value_offsets_buffer, children)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::array::StringBuilder;
+    use crate::datatypes::{Float64Type, Int32Type};
+
+    #[test]
+    fn append_any_rejects_primitive_type() {
+        let mut builder = UnionBuilder::new_dense();
+        let err = builder
+            .append_any("a", DataType::Int32, |b| {
+                b.as_any_mut()
+                    .downcast_mut::<crate::array::Int32Builder>()
+                    .unwrap()
+                    .append_value(1)
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("does not support primitive type"));
+    }
+
+    #[test]
+    fn append_any_then_mismatched_primitive_reuse_is_rejected() {
+        let mut builder = UnionBuilder::new_dense();
+        builder
+            .append_any("a", DataType::Utf8, |b| {
+                b.as_any_mut()
+                    .downcast_mut::<StringBuilder>()
+                    .unwrap()
+                    .append_value("foo")
+            })
+            .unwrap();
+
+        let err = builder.append::<Int32Type>("a", 1).unwrap_err();
+        assert!(err.to_string().contains("doesn't match existing type"));
+    }
+
+    #[test]
+    fn append_values_rejects_array_backed_field_reuse() {
+        let mut builder = UnionBuilder::new_dense();
+        builder
+            .append_any("a", DataType::Utf8, |b| {
+                b.as_any_mut()
+                    .downcast_mut::<StringBuilder>()
+                    .unwrap()
+                    .append_value("foo")
+            })
+            .unwrap();
+
+        let err = builder
+            .append_values::<Int32Type>("a", &[1, 2, 3], None)
+            .unwrap_err();
+        assert!(err.to_string().contains("already created via append_any"));
+    }
+
+    #[test]
+    fn append_values_rejects_mismatched_validity_length() {
+        let mut builder = UnionBuilder::new_dense();
+        let err = builder
+            .append_values::<Int32Type>("a", &[1, 2, 3], Some(&[true, false]))
+            .unwrap_err();
+        assert!(err.to_string().contains("doesn't match values length"));
+    }
+
+    #[test]
+    fn append_slice_accumulates_like_repeated_append() {
+        let mut builder = UnionBuilder::new_dense();
+        builder.append::<Int32Type>("a", 1).unwrap();
+        builder.append_slice::<Int32Type>("a", &[2, 3, 4]).unwrap();
+
+        assert_eq!(builder.len, 4);
+        assert_eq!(builder.fields.get("a").unwrap().slots, 4);
+    }
+
+    #[test]
+    fn append_values_on_sparse_union_records_one_position_per_row() {
+        let mut builder = UnionBuilder::new_sparse();
+        builder.append::<Int32Type>("a", 1).unwrap();
+        builder
+            .append_values::<Int32Type>("a", &[2, 3], Some(&[true, false]))
+            .unwrap();
+
+        let field = builder.fields.get("a").unwrap();
+        assert_eq!(field.slots, 3);
+        assert_eq!(field.sparse_positions.as_ref().unwrap(), &[0, 1, 2]);
+    }
+
+    #[test]
+    fn next_type_id_finds_low_gap_left_by_an_explicit_reservation() {
+        let mut builder = UnionBuilder::new_dense();
+        builder.append_with_type_id::<Int32Type>("a", 5, 1).unwrap();
+
+        // "a" is the only field, so a naive `fields.len()`-based search would
+        // start at 1 and never consider id 0.
+        assert_eq!(builder.next_type_id().unwrap(), 0);
+    }
+
+    #[test]
+    fn next_type_id_errors_instead_of_panicking_once_all_ids_are_claimed() {
+        let mut builder = UnionBuilder::new_dense();
+        for id in 0..=i8::MAX {
+            builder
+                .append_with_type_id::<Int32Type>(&id.to_string(), id, 0)
+                .unwrap();
+        }
+
+        let err = builder.next_type_id().unwrap_err();
+        assert!(err.to_string().contains("all already in use"));
+    }
+
+    #[test]
+    fn union_arrays_eq_dense_and_sparse_encodings_of_same_sequence() {
+        let mut dense = UnionBuilder::new_dense();
+        dense.append::<Int32Type>("a", 1).unwrap();
+        dense.append::<Float64Type>("b", 3.0).unwrap();
+        dense.append::<Int32Type>("a", 4).unwrap();
+        let dense = dense.build().unwrap();
+
+        let mut sparse = UnionBuilder::new_sparse();
+        sparse.append::<Int32Type>("a", 1).unwrap();
+        sparse.append::<Float64Type>("b", 3.0).unwrap();
+        sparse.append::<Int32Type>("a", 4).unwrap();
+        let sparse = sparse.build().unwrap();
+
+        assert!(union_arrays_eq(&dense, &sparse));
+    }
+
+    #[test]
+    fn union_arrays_eq_treats_nulls_in_children_as_equal() {
+        let mut a = UnionBuilder::new_dense();
+        a.append::<Int32Type>("a", 1).unwrap();
+        a.append_null::<Int32Type>("a").unwrap();
+        let a = a.build().unwrap();
+
+        let mut b = UnionBuilder::new_sparse();
+        b.append::<Int32Type>("a", 1).unwrap();
+        b.append_null::<Int32Type>("a").unwrap();
+        let b = b.build().unwrap();
+
+        assert!(union_arrays_eq(&a, &b));
+    }
+
+    #[test]
+    fn union_arrays_eq_is_false_for_differing_values() {
+        let mut a = UnionBuilder::new_dense();
+        a.append::<Int32Type>("a", 1).unwrap();
+        let a = a.build().unwrap();
+
+        let mut b = UnionBuilder::new_dense();
+        b.append::<Int32Type>("a", 2).unwrap();
+        let b = b.build().unwrap();
+
+        assert!(!union_arrays_eq(&a, &b));
+    }
+
+    #[test]
+    fn union_arrays_eq_is_false_when_type_ids_disagree_on_which_variant_is_selected() {
+        // `a`: "x" is pinned to type_id 0 and appended first (row 0 = 42);
+        // "y" is pinned to type_id 1 and appended second (row 1, unused).
+        let mut a = UnionBuilder::new_dense();
+        a.append_with_type_id::<Int32Type>("x", 0, 42).unwrap();
+        a.append_with_type_id::<Int32Type>("y", 1, 7).unwrap();
+        let a = a.build().unwrap();
+
+        // `b`: "q" is pinned to type_id 1 and appended *first* (row 0 = 42);
+        // "p" is pinned to type_id 0 and appended second (row 1, unused). So
+        // a.type_id(0) == 0 while b.type_id(0) == 1, yet both type ids
+        // happen to resolve to an Int32 child whose value at its respective
+        // offset is 42 — a comparison that only looked at each array's own
+        // type id to resolve a child (ignoring whether the ids themselves
+        // agree) would wrongly call row 0 equal between the two arrays.
+        let mut b = UnionBuilder::new_dense();
+        b.append_with_type_id::<Int32Type>("q", 1, 42).unwrap();
+        b.append_with_type_id::<Int32Type>("p", 0, 7).unwrap();
+        let b = b.build().unwrap();
+
+        assert!(!union_arrays_eq(&a, &b));
+    }
+}